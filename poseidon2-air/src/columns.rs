@@ -0,0 +1,145 @@
+use core::borrow::{Borrow, BorrowMut};
+use core::marker::PhantomData;
+use core::mem::size_of;
+
+use crate::sbox_mode::SboxMode;
+
+/// Columns for a single Poseidon2 permutation.
+#[repr(C)]
+pub struct Poseidon2Cols<
+    T,
+    const WIDTH: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+    M: SboxMode,
+> {
+    pub inputs: [T; WIDTH],
+
+    pub beginning_full_rounds:
+        [FullRound<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS>; HALF_FULL_ROUNDS],
+    pub partial_rounds: [PartialRound<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS>; PARTIAL_ROUNDS],
+    pub ending_full_rounds: [FullRound<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS>; HALF_FULL_ROUNDS],
+    pub(crate) _mode: PhantomData<M>,
+}
+
+/// Columns for a single full round in a Poseidon2 permutation.
+#[repr(C)]
+pub struct FullRound<T, const WIDTH: usize, const SBOX_DEGREE: usize, const SBOX_REGISTERS: usize>
+{
+    pub post: [T; WIDTH],
+    pub sbox: [SBox<T, SBOX_DEGREE, SBOX_REGISTERS>; WIDTH],
+}
+
+/// Columns for a single partial round in a Poseidon2 permutation.
+#[repr(C)]
+pub struct PartialRound<
+    T,
+    const WIDTH: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+> {
+    pub post_sbox: T,
+    pub sbox: SBox<T, SBOX_DEGREE, SBOX_REGISTERS>,
+}
+
+/// Intermediate registers needed to constrain an S-box that can't be expressed as a
+/// single-degree constraint.
+///
+/// In [`crate::sbox_mode::Forward`] mode each entry holds a partial `DEGREE`-th power of the
+/// S-box input, with the final entry equal to the S-box output. In
+/// [`crate::sbox_mode::Inverse`] mode the roles are reversed: the final entry holds the S-box
+/// *output* (supplied as a witness), and the preceding entries constrain that output's
+/// `DEGREE`-th power back to the S-box input.
+#[repr(C)]
+pub struct SBox<T, const DEGREE: usize, const REGISTERS: usize>(pub [T; REGISTERS]);
+
+pub const fn num_cols<
+    const WIDTH: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+    M: SboxMode,
+>() -> usize {
+    size_of::<Poseidon2Cols<u8, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>>()
+}
+
+impl<
+        T,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    >
+    Borrow<
+        Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+    > for [T]
+{
+    fn borrow(
+        &self,
+    ) -> &Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+    {
+        debug_assert_eq!(
+            self.len(),
+            num_cols::<WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>()
+        );
+        let (prefix, shorts, suffix) = unsafe {
+            self.align_to::<Poseidon2Cols<
+                T,
+                WIDTH,
+                SBOX_DEGREE,
+                SBOX_REGISTERS,
+                HALF_FULL_ROUNDS,
+                PARTIAL_ROUNDS,
+                M,
+            >>()
+        };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &shorts[0]
+    }
+}
+
+impl<
+        T,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    >
+    BorrowMut<
+        Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+    > for [T]
+{
+    fn borrow_mut(
+        &mut self,
+    ) -> &mut Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+    {
+        debug_assert_eq!(
+            self.len(),
+            num_cols::<WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>()
+        );
+        let (prefix, shorts, suffix) = unsafe {
+            self.align_to_mut::<Poseidon2Cols<
+                T,
+                WIDTH,
+                SBOX_DEGREE,
+                SBOX_REGISTERS,
+                HALF_FULL_ROUNDS,
+                PARTIAL_ROUNDS,
+                M,
+            >>()
+        };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &mut shorts[0]
+    }
+}
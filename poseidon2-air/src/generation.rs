@@ -0,0 +1,213 @@
+use core::borrow::BorrowMut;
+
+use p3_field::{AbstractField, PrimeField64};
+
+use crate::sbox_mode::SboxMode;
+use crate::{FullRound, PartialRound, Poseidon2Air, Poseidon2Cols, SBox};
+
+/// The modular inverse of `DEGREE` mod `p - 1`, i.e. the exponent `e` such that raising to the
+/// `e`-th power undoes raising to the `DEGREE`-th power over `F`.
+///
+/// `DEGREE` must be coprime to `p - 1` (this is what makes `x -> x^DEGREE` a bijection on `F` in
+/// the first place), so the inverse always exists.
+fn inverse_exponent<F: PrimeField64>(degree: u64) -> u64 {
+    let modulus = F::ORDER_U64 - 1;
+    let (mut old_r, mut r) = (degree as i128, modulus as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+    }
+    assert_eq!(old_r, 1, "DEGREE must be coprime to F's multiplicative order");
+    old_s.rem_euclid(modulus as i128) as u64
+}
+
+/// Fills `sbox`'s intermediate registers for a native (non-symbolic) S-box evaluation, and
+/// returns its output.
+///
+/// In [`Forward`](crate::sbox_mode::Forward) mode this is a direct `DEGREE`-th power. In
+/// [`Inverse`](crate::sbox_mode::Inverse) mode it instead computes the `DEGREE`-th root of `x`
+/// via modular exponentiation by [`inverse_exponent`] -- expensive compared to the forward
+/// direction, which is exactly why the inverse S-box is only attractive when it's the AIR
+/// constraint, not the native evaluation, that needs to be cheap.
+///
+/// The forward chain computes the `2^REGISTERS + 1`-th power, while the inverse root is taken
+/// with respect to `DEGREE`; both agree with [`crate::air`]'s constraints only when
+/// `DEGREE == 2^REGISTERS + 1`.
+pub(crate) fn fill_sbox<F: PrimeField64, M: SboxMode, const DEGREE: usize, const REGISTERS: usize>(
+    sbox: &mut SBox<F, DEGREE, REGISTERS>,
+    x: F,
+) -> F {
+    assert_ne!(REGISTERS, 0, "The S-box is unused, so it shouldn't have any registers");
+    if M::INVERSE {
+        let out = x.exp_u64(inverse_exponent::<F>(DEGREE as u64));
+        let mut previous = out * out;
+        for i in 0..REGISTERS - 1 {
+            sbox.0[i] = previous;
+            previous *= previous;
+        }
+        sbox.0[REGISTERS - 1] = out;
+        out
+    } else {
+        let mut previous = x * x;
+        for i in 0..REGISTERS - 1 {
+            sbox.0[i] = previous;
+            previous *= previous;
+        }
+        let out = previous * x;
+        sbox.0[REGISTERS - 1] = out;
+        out
+    }
+}
+
+/// The native-evaluation counterpart of [`crate::air::matmul_external`]: applies the external
+/// linear layer to `state` in place.
+fn matmul_external<F: AbstractField, const WIDTH: usize>(state: &mut [F; WIDTH]) {
+    let sum: F = state.iter().cloned().sum();
+    for s in state.iter_mut() {
+        *s = s.clone() + sum.clone();
+    }
+}
+
+/// The native-evaluation counterpart of [`crate::air::matmul_internal`]: applies the internal
+/// linear layer to `state` in place.
+fn matmul_internal<F: AbstractField, const WIDTH: usize>(
+    state: &mut [F; WIDTH],
+    internal_matrix_diagonal: &[F; WIDTH],
+) {
+    let sum: F = state.iter().cloned().sum();
+    for (s, diag) in state.iter_mut().zip(internal_matrix_diagonal) {
+        *s = s.clone() * diag.clone() + sum.clone();
+    }
+}
+
+/// Fills one full round's witness columns (including its S-box registers) for a native
+/// permutation over `state`, advancing `state` to its post-round value.
+fn fill_full_round<F: PrimeField64, M: SboxMode, const WIDTH: usize, const SBOX_DEGREE: usize, const SBOX_REGISTERS: usize>(
+    state: &mut [F; WIDTH],
+    full_round: &mut FullRound<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS>,
+    round_constants: &[F; WIDTH],
+) {
+    for (i, s) in state.iter_mut().enumerate() {
+        *s = *s + round_constants[i];
+        *s = fill_sbox::<F, M, SBOX_DEGREE, SBOX_REGISTERS>(&mut full_round.sbox[i], *s);
+    }
+    matmul_external::<F, WIDTH>(state);
+    full_round.post = *state;
+}
+
+/// Fills one partial round's witness columns (including its S-box registers) for a native
+/// permutation over `state`, advancing `state` to its post-round value.
+fn fill_partial_round<F: PrimeField64, M: SboxMode, const WIDTH: usize, const SBOX_DEGREE: usize, const SBOX_REGISTERS: usize>(
+    state: &mut [F; WIDTH],
+    partial_round: &mut PartialRound<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS>,
+    round_constant: F,
+    internal_matrix_diagonal: &[F; WIDTH],
+) {
+    state[0] += round_constant;
+    state[0] = fill_sbox::<F, M, SBOX_DEGREE, SBOX_REGISTERS>(&mut partial_round.sbox, state[0]);
+    partial_round.post_sbox = state[0];
+    matmul_internal::<F, WIDTH>(state, internal_matrix_diagonal);
+}
+
+/// Fills `row`'s witness columns for a single Poseidon2 permutation over `inputs`, and returns
+/// the permutation's output.
+///
+/// This is the native-evaluation counterpart of [`crate::air::eval`], invoked by
+/// [`Poseidon2Air::generate_trace_row`]; it's the entry point that actually drives [`fill_sbox`]
+/// (and so [`crate::sbox_mode::Inverse`]'s witnessed S-box output) rather than leaving it dead
+/// code.
+pub(crate) fn generate_trace_row<
+    F: PrimeField64,
+    M: SboxMode,
+    const WIDTH: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+>(
+    air: &Poseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+    row: &mut [F],
+    inputs: [F; WIDTH],
+) -> [F; WIDTH] {
+    let cols: &mut Poseidon2Cols<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M> =
+        row.borrow_mut();
+    cols.inputs = inputs;
+
+    let mut state = inputs;
+    matmul_external::<F, WIDTH>(&mut state);
+    for (full_round, round_constants) in cols
+        .beginning_full_rounds
+        .iter_mut()
+        .zip(&air.constants.beginning_full_round_constants)
+    {
+        fill_full_round::<F, M, WIDTH, SBOX_DEGREE, SBOX_REGISTERS>(&mut state, full_round, round_constants);
+    }
+    for (partial_round, round_constant) in cols
+        .partial_rounds
+        .iter_mut()
+        .zip(air.constants.partial_round_constants)
+    {
+        fill_partial_round::<F, M, WIDTH, SBOX_DEGREE, SBOX_REGISTERS>(
+            &mut state,
+            partial_round,
+            round_constant,
+            &air.constants.internal_matrix_diagonal,
+        );
+    }
+    for (full_round, round_constants) in cols
+        .ending_full_rounds
+        .iter_mut()
+        .zip(&air.constants.ending_full_round_constants)
+    {
+        fill_full_round::<F, M, WIDTH, SBOX_DEGREE, SBOX_REGISTERS>(&mut state, full_round, round_constants);
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+
+    use super::*;
+    use crate::sbox_mode::{Forward, Inverse};
+
+    /// `SBOX_DEGREE` must equal `2 ^ SBOX_REGISTERS + 1` for [`fill_sbox`]'s squaring chain, and
+    /// must be coprime to `BabyBear`'s `p - 1` for the inverse S-box to exist; 17 (with 4
+    /// registers) satisfies both.
+    const SBOX_DEGREE: usize = 17;
+    const SBOX_REGISTERS: usize = 4;
+
+    #[test]
+    fn inverse_sbox_undoes_forward_sbox() {
+        let x = BabyBear::from_canonical_u64(1234);
+
+        let mut forward_sbox: SBox<BabyBear, SBOX_DEGREE, SBOX_REGISTERS> =
+            SBox([BabyBear::zero(); SBOX_REGISTERS]);
+        let y = fill_sbox::<BabyBear, Forward, SBOX_DEGREE, SBOX_REGISTERS>(&mut forward_sbox, x);
+        assert_ne!(y, x);
+
+        let mut inverse_sbox: SBox<BabyBear, SBOX_DEGREE, SBOX_REGISTERS> =
+            SBox([BabyBear::zero(); SBOX_REGISTERS]);
+        let recovered = fill_sbox::<BabyBear, Inverse, SBOX_DEGREE, SBOX_REGISTERS>(&mut inverse_sbox, y);
+        assert_eq!(recovered, x);
+    }
+
+    #[test]
+    fn generate_trace_row_is_deterministic() {
+        let air: Poseidon2Air<BabyBear, 8, SBOX_DEGREE, SBOX_REGISTERS, 2, 3, Forward> =
+            Poseidon2Air::new_from_seed(b"generation test seed");
+        let inputs = core::array::from_fn(BabyBear::from_canonical_usize);
+
+        let width = crate::columns::num_cols::<8, SBOX_DEGREE, SBOX_REGISTERS, 2, 3, Forward>();
+        let mut row_a = vec![BabyBear::zero(); width];
+        let mut row_b = vec![BabyBear::zero(); width];
+        let output_a = generate_trace_row(&air, &mut row_a, inputs);
+        let output_b = generate_trace_row(&air, &mut row_b, inputs);
+
+        assert_eq!(output_a, output_b);
+        assert_eq!(row_a, row_b);
+        assert_ne!(output_a, inputs, "a permutation should not be the identity");
+    }
+}
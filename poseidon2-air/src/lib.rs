@@ -0,0 +1,19 @@
+//! An AIR for the Poseidon2 permutation.
+
+mod air;
+mod columns;
+mod generation;
+mod merkle;
+mod packed;
+pub(crate) mod seed;
+mod sbox_mode;
+mod sponge;
+mod vectorized;
+
+pub use air::*;
+pub use columns::*;
+pub use merkle::*;
+pub use packed::*;
+pub use sbox_mode::*;
+pub use sponge::*;
+pub use vectorized::*;
@@ -0,0 +1,236 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+
+use crate::air::eval;
+use crate::sbox_mode::SboxMode;
+use crate::{Poseidon2Cols, VectorizedPoseidon2Air};
+
+/// Columns for a single row of a [`MerklePathAir`]: one 2-to-1 compression along an
+/// authentication path.
+#[repr(C)]
+pub struct MerklePathCols<
+    T,
+    const DIGEST_WIDTH: usize,
+    const WIDTH: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+    M: SboxMode,
+> {
+    /// 1 if the running digest is this row's *right* child (so the sibling goes first in the
+    /// rate), 0 if it's the left child.
+    pub is_right_child: T,
+    /// The sibling digest authenticating this row's step.
+    pub sibling: [T; DIGEST_WIDTH],
+    /// The compression permutation: `perm.inputs`'s `2 * DIGEST_WIDTH == WIDTH` lanes hold
+    /// `(current_digest, sibling)` or `(sibling, current_digest)`, ordered by `is_right_child`.
+    /// There are no leftover capacity lanes to leave unconstrained: the digest and sibling
+    /// exactly fill the permutation's width (see [`MerklePathAir::new`]).
+    pub perm: Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+}
+
+/// An AIR constraining a Merkle authentication path as a chain of 2-to-1 Poseidon2
+/// compressions, built on top of [`VectorizedPoseidon2Air`] (with one permutation per row):
+/// each row absorbs the running digest and a sibling, ordered by a direction bit, and its
+/// output feeds the next row's running digest. The first row's leaf and the last row's root are
+/// exposed as public values, so this doubles as a reusable membership-proof subsystem without a
+/// separate lookup table.
+///
+/// Unlike [`crate::sponge::Poseidon2SpongeAir`], there is no separate capacity: the digest and
+/// sibling must exactly fill the permutation's width (`2 * DIGEST_WIDTH == WIDTH`), since any
+/// leftover lanes would be neither initialized nor chained between rows and so would be fully
+/// prover-controlled at every step.
+pub struct MerklePathAir<
+    F: Field,
+    const DIGEST_WIDTH: usize,
+    const WIDTH: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+    M: SboxMode,
+> {
+    permutation:
+        VectorizedPoseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, 1, M>,
+}
+
+impl<
+        F: Field,
+        const DIGEST_WIDTH: usize,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    >
+    MerklePathAir<F, DIGEST_WIDTH, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    pub fn new(
+        permutation: VectorizedPoseidon2Air<
+            F,
+            WIDTH,
+            SBOX_DEGREE,
+            SBOX_REGISTERS,
+            HALF_FULL_ROUNDS,
+            PARTIAL_ROUNDS,
+            1,
+            M,
+        >,
+    ) -> Self {
+        assert_eq!(
+            2 * DIGEST_WIDTH,
+            WIDTH,
+            "the running digest and the sibling must exactly fill the rate, leaving no \
+             capacity lanes uninitialized and unchained between rows"
+        );
+        Self { permutation }
+    }
+}
+
+impl<
+        F: Field,
+        const DIGEST_WIDTH: usize,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > BaseAir<F>
+    for MerklePathAir<F, DIGEST_WIDTH, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    fn width(&self) -> usize {
+        crate::columns::num_cols::<WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>()
+            + DIGEST_WIDTH
+            + 1
+    }
+}
+
+impl<
+        AB: AirBuilderWithPublicValues,
+        const DIGEST_WIDTH: usize,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > Air<AB>
+    for MerklePathAir<AB::F, DIGEST_WIDTH, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    #[inline]
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let local: &MerklePathCols<
+            AB::Var,
+            DIGEST_WIDTH,
+            WIDTH,
+            SBOX_DEGREE,
+            SBOX_REGISTERS,
+            HALF_FULL_ROUNDS,
+            PARTIAL_ROUNDS,
+            M,
+        > = (*local).borrow();
+        let next: &MerklePathCols<
+            AB::Var,
+            DIGEST_WIDTH,
+            WIDTH,
+            SBOX_DEGREE,
+            SBOX_REGISTERS,
+            HALF_FULL_ROUNDS,
+            PARTIAL_ROUNDS,
+            M,
+        > = (*next).borrow();
+
+        builder.assert_bool(local.is_right_child.clone());
+
+        eval(self.permutation.permutation(), builder, &local.perm);
+
+        // Conditional swap: the sibling always occupies whichever rate half the running digest
+        // doesn't.
+        let local_is_right = local.is_right_child.clone();
+        let local_is_left = AB::Expr::one() - local_is_right.clone();
+        for i in 0..DIGEST_WIDTH {
+            builder
+                .when(local_is_left.clone())
+                .assert_eq(local.perm.inputs[DIGEST_WIDTH + i].clone(), local.sibling[i].clone());
+            builder
+                .when(local_is_right.clone())
+                .assert_eq(local.perm.inputs[i].clone(), local.sibling[i].clone());
+        }
+
+        // Row-to-row linking: this row's compression output becomes the next row's running
+        // digest, landing in whichever half `next`'s direction bit selects.
+        let local_output = local.perm.output();
+        let next_is_right = next.is_right_child.clone();
+        let next_is_left = AB::Expr::one() - next_is_right.clone();
+        for i in 0..DIGEST_WIDTH {
+            builder
+                .when_transition()
+                .when(next_is_left.clone())
+                .assert_eq(next.perm.inputs[i].clone(), local_output[i].clone());
+            builder
+                .when_transition()
+                .when(next_is_right.clone())
+                .assert_eq(next.perm.inputs[DIGEST_WIDTH + i].clone(), local_output[i].clone());
+        }
+
+        // Boundary constraints: the first row's leaf and the last row's root are public.
+        let public_values = builder.public_values();
+        let leaf: Vec<AB::PublicVar> = public_values[..DIGEST_WIDTH].to_vec();
+        let root: Vec<AB::PublicVar> = public_values[DIGEST_WIDTH..2 * DIGEST_WIDTH].to_vec();
+        for (i, leaf_i) in leaf.iter().enumerate() {
+            builder
+                .when_first_row()
+                .when(local_is_left.clone())
+                .assert_eq(local.perm.inputs[i].clone(), leaf_i.clone().into());
+            builder
+                .when_first_row()
+                .when(local_is_right.clone())
+                .assert_eq(local.perm.inputs[DIGEST_WIDTH + i].clone(), leaf_i.clone().into());
+        }
+        for (output_i, root_i) in local_output[..DIGEST_WIDTH].iter().zip(root) {
+            builder
+                .when_last_row()
+                .assert_eq(output_i.clone(), root_i.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::sbox_mode::Forward;
+
+    #[test]
+    fn accepts_a_digest_and_sibling_that_exactly_fill_the_width() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let permutation: VectorizedPoseidon2Air<BabyBear, 8, 17, 4, 2, 3, 1, Forward> =
+            VectorizedPoseidon2Air::new_from_rng(&mut rng);
+        let _: MerklePathAir<BabyBear, 4, 8, 17, 4, 2, 3, Forward> = MerklePathAir::new(permutation);
+    }
+
+    /// Regression test: before requiring `2 * DIGEST_WIDTH == WIDTH`, leftover capacity lanes
+    /// (here `WIDTH - 2 * DIGEST_WIDTH == 2`) were never initialized or chained row-to-row, and
+    /// so were fully prover-controlled at every step.
+    #[test]
+    #[should_panic(expected = "exactly fill the rate")]
+    fn rejects_leftover_capacity_lanes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oversized_permutation: VectorizedPoseidon2Air<BabyBear, 10, 17, 4, 2, 3, 1, Forward> =
+            VectorizedPoseidon2Air::new_from_rng(&mut rng);
+        let _: MerklePathAir<BabyBear, 4, 10, 17, 4, 2, 3, Forward> =
+            MerklePathAir::new(oversized_permutation);
+    }
+}
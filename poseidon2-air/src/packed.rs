@@ -0,0 +1,174 @@
+use core::borrow::{Borrow, BorrowMut};
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::Field;
+use p3_matrix::Matrix;
+
+use crate::air::eval;
+use crate::sbox_mode::SboxMode;
+use crate::{Poseidon2Air, Poseidon2Cols};
+
+/// A version of [`crate::vectorized::VectorizedPoseidon2Air`] that packs as many permutations
+/// into a row as fit a caller-chosen target row length, rather than a compile-time
+/// `VECTOR_LEN`. This trades [`crate::vectorized::VectorizedPoseidon2Cols`]'s fixed-size lane
+/// array for a permutation count computed once at construction time, so the same AIR type can
+/// be reused across traces with different target widths (e.g. to match an unrelated AIR's width
+/// in a shared STARK) without a new const-generic instantiation.
+pub struct PackedPoseidon2Air<
+    F: Field,
+    const WIDTH: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+    M: SboxMode,
+> {
+    air: Poseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+    num_permutations: usize,
+}
+
+impl<
+        F: Field,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > PackedPoseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    /// Packs as many copies of `air` as fit into a row of `target_row_len` columns, rounding
+    /// down. Panics if not even one permutation fits.
+    pub fn new(
+        air: Poseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+        target_row_len: usize,
+    ) -> Self {
+        let stride = air.width();
+        let num_permutations = target_row_len / stride;
+        assert!(
+            num_permutations > 0,
+            "target_row_len {target_row_len} is too small to fit a single permutation of width {stride}"
+        );
+        Self { air, num_permutations }
+    }
+
+    /// The number of permutations packed into each row, computed once in [`Self::new`].
+    pub fn num_permutations(&self) -> usize {
+        self.num_permutations
+    }
+
+    /// The number of columns occupied by a single packed permutation.
+    pub fn stride(&self) -> usize {
+        self.air.width()
+    }
+
+    /// A safe view of the `i`-th permutation's columns within `row`.
+    ///
+    /// `row` need not be exactly [`BaseAir::width`] columns long: only the `i`-th
+    /// `stride`-column sub-slice is read, so this also serves trace generation over a
+    /// `RowMajorMatrix` row that may be padded wider than `self.width()`.
+    pub fn permutation<'a, T>(
+        &self,
+        row: &'a [T],
+        i: usize,
+    ) -> &'a Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M> {
+        let stride = self.stride();
+        row[i * stride..(i + 1) * stride].borrow()
+    }
+
+    /// A safe mutable view of the `i`-th permutation's columns within `row`.
+    pub fn permutation_mut<'a, T>(
+        &self,
+        row: &'a mut [T],
+        i: usize,
+    ) -> &'a mut Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+    {
+        let stride = self.stride();
+        row[i * stride..(i + 1) * stride].borrow_mut()
+    }
+}
+
+impl<
+        F: Field,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > BaseAir<F>
+    for PackedPoseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    fn width(&self) -> usize {
+        self.air.width() * self.num_permutations
+    }
+}
+
+impl<
+        AB: AirBuilder,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > Air<AB>
+    for PackedPoseidon2Air<AB::F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    #[inline]
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let row: &[AB::Var] = &local;
+        for i in 0..self.num_permutations {
+            eval(&self.air, builder, self.permutation(row, i));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::AbstractField;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::sbox_mode::Forward;
+
+    fn air() -> Poseidon2Air<BabyBear, 8, 17, 4, 2, 3, Forward> {
+        Poseidon2Air::new_from_rng(&mut StdRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn packs_as_many_permutations_as_fit_rounding_down() {
+        let stride = air().width();
+        let packed = PackedPoseidon2Air::new(air(), stride * 3 + 1);
+        assert_eq!(packed.num_permutations(), 3);
+        assert_eq!(packed.stride(), stride);
+        assert_eq!(BaseAir::<BabyBear>::width(&packed), stride * 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "too small to fit a single permutation")]
+    fn rejects_a_target_row_len_too_small_for_one_permutation() {
+        let stride = air().width();
+        PackedPoseidon2Air::new(air(), stride - 1);
+    }
+
+    #[test]
+    fn permutation_and_permutation_mut_see_the_same_lane() {
+        let stride = air().width();
+        let packed = PackedPoseidon2Air::new(air(), stride * 2);
+        let mut row = vec![BabyBear::zero(); stride * 2];
+
+        packed.permutation_mut(&mut row, 1).inputs = [BabyBear::from_canonical_u64(42); 8];
+
+        assert_eq!(
+            packed.permutation(&row, 1).inputs,
+            [BabyBear::from_canonical_u64(42); 8]
+        );
+        // Writing into lane 1 must not disturb lane 0's columns.
+        assert_eq!(packed.permutation(&row, 0).inputs, [BabyBear::zero(); 8]);
+    }
+}
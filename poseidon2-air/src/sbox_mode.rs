@@ -0,0 +1,29 @@
+/// Selects how a round's S-box constraint is oriented.
+///
+/// Threaded through [`crate::Poseidon2Air`] and [`crate::Poseidon2Cols`] (and everything built
+/// on top of them) as a type parameter so that a single crate build can emit either variant
+/// without a runtime branch in the constraint system.
+pub trait SboxMode: 'static + Send + Sync {
+    /// `false` for the ordinary forward S-box (`out = in ^ DEGREE`), `true` for the inverse
+    /// S-box (`in = out ^ DEGREE`, with `out` supplied as a witness).
+    const INVERSE: bool;
+}
+
+/// The ordinary forward S-box: `out = in ^ DEGREE`. Cheap to evaluate natively, and the
+/// constraint is likewise a direct forward power.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Forward;
+
+impl SboxMode for Forward {
+    const INVERSE: bool = false;
+}
+
+/// The inverse S-box: `out = in ^ (1 / DEGREE)`. Expensive to evaluate natively (it's a modular
+/// exponentiation by `DEGREE`'s inverse mod `p - 1`), but just as cheap to constrain, since the
+/// constraint is flipped to `in = out ^ DEGREE` with `out` supplied as a witness column.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Inverse;
+
+impl SboxMode for Inverse {
+    const INVERSE: bool = true;
+}
@@ -0,0 +1,125 @@
+use core::array;
+
+use p3_field::PrimeField64;
+
+/// Domain separation tag for the external (full-round) constants.
+const FULL_ROUND_TAG: &[u8] = b"p3-poseidon2-air/full-round";
+/// Domain separation tag for the internal (partial-round) constants.
+const PARTIAL_ROUND_TAG: &[u8] = b"p3-poseidon2-air/partial-round";
+/// Domain separation tag for the internal linear layer's diagonal entries.
+const MATRIX_DIAGONAL_TAG: &[u8] = b"p3-poseidon2-air/matrix-diagonal";
+
+/// Deterministically derives `count` field elements from `seed`, domain-separated by `tag`.
+///
+/// Each candidate is produced by keyed-hashing `tag` together with `seed` and a running
+/// counter, then masking the low 8 bytes of the digest down to `F::ORDER_U64`'s own bit width
+/// before interpreting them as a little-endian integer. Masking to the modulus's bit width
+/// (rather than a fixed 64-bit window) keeps the rejection rate below 50% regardless of how
+/// small `F` is: for a ~31-bit field like BabyBear, comparing a raw 64-bit candidate against
+/// `F::ORDER_U64` would accept only about one in nine billion candidates, making constant
+/// generation impractically slow. Candidates `>= F::ORDER_U64` are rejected and the counter is
+/// advanced, so the output is bit-for-bit reproducible given the same `seed` regardless of
+/// platform.
+fn derive_constants<F: PrimeField64>(seed: &[u8], tag: &[u8], count: usize) -> Vec<F> {
+    let key = *blake3::hash(tag).as_bytes();
+    let mask = candidate_mask::<F>();
+    let mut constants = Vec::with_capacity(count);
+    let mut counter: u64 = 0;
+    while constants.len() < count {
+        let mut message = Vec::with_capacity(seed.len() + 8);
+        message.extend_from_slice(seed);
+        message.extend_from_slice(&counter.to_le_bytes());
+        counter += 1;
+
+        let digest = blake3::keyed_hash(&key, &message);
+        let candidate = u64::from_le_bytes(digest.as_bytes()[..8].try_into().unwrap()) & mask;
+        if candidate < F::ORDER_U64 {
+            constants.push(F::from_canonical_u64(candidate));
+        }
+    }
+    constants
+}
+
+/// The bitmask that sizes a rejection-sampling candidate to `F::ORDER_U64`'s own bit width,
+/// i.e. `2.pow(ceil(log2(F::ORDER_U64))) - 1`.
+fn candidate_mask<F: PrimeField64>() -> u64 {
+    let bits = u64::BITS - (F::ORDER_U64 - 1).leading_zeros();
+    if bits >= u64::BITS {
+        u64::MAX
+    } else {
+        (1u64 << bits) - 1
+    }
+}
+
+/// Derives a `[F; WIDTH]` block of full-round constants for round `round_index`.
+pub(crate) fn full_round_constants<F: PrimeField64, const WIDTH: usize>(
+    seed: &[u8],
+    round_index: usize,
+) -> [F; WIDTH] {
+    let mut tagged_seed = seed.to_vec();
+    tagged_seed.extend_from_slice(&(round_index as u64).to_le_bytes());
+    let constants = derive_constants::<F>(&tagged_seed, FULL_ROUND_TAG, WIDTH);
+    array::from_fn(|i| constants[i])
+}
+
+/// Derives the `PARTIAL_ROUNDS` partial-round constants in one shot.
+pub(crate) fn partial_round_constants<F: PrimeField64, const PARTIAL_ROUNDS: usize>(
+    seed: &[u8],
+) -> [F; PARTIAL_ROUNDS] {
+    let constants = derive_constants::<F>(seed, PARTIAL_ROUND_TAG, PARTIAL_ROUNDS);
+    array::from_fn(|i| constants[i])
+}
+
+/// Derives the `WIDTH` diagonal entries of the internal linear layer.
+pub(crate) fn internal_matrix_diagonal<F: PrimeField64, const WIDTH: usize>(
+    seed: &[u8],
+) -> [F; WIDTH] {
+    let constants = derive_constants::<F>(seed, MATRIX_DIAGONAL_TAG, WIDTH);
+    array::from_fn(|i| constants[i])
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+
+    use super::*;
+
+    #[test]
+    fn same_seed_reproduces_the_same_constants() {
+        let seed = b"p3-poseidon2-air test seed";
+        let a: [BabyBear; 16] = full_round_constants(seed, 0);
+        let b: [BabyBear; 16] = full_round_constants(seed, 0);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn different_round_indices_diverge() {
+        let seed = b"p3-poseidon2-air test seed";
+        let a: [BabyBear; 16] = full_round_constants(seed, 0);
+        let b: [BabyBear; 16] = full_round_constants(seed, 1);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        let a: [BabyBear; 16] = internal_matrix_diagonal(b"seed-a");
+        let b: [BabyBear; 16] = internal_matrix_diagonal(b"seed-b");
+        assert_ne!(a, b);
+    }
+
+    /// Regression test for masking the rejection-sampling candidate to the field's own bit
+    /// width: against a ~31-bit field like BabyBear, masking to a fixed 64-bit window instead
+    /// would make this call need billions of blake3 evaluations in expectation.
+    #[test]
+    fn derivation_over_a_small_field_terminates_promptly() {
+        let seed = b"p3-poseidon2-air test seed";
+        let constants: [BabyBear; 13] = partial_round_constants(seed);
+        assert_eq!(constants.len(), 13);
+    }
+
+    #[test]
+    fn candidate_mask_is_the_modulus_bit_width() {
+        // BabyBear's order is 2^31 - 2^27 + 1, a 31-bit number.
+        assert_eq!(candidate_mask::<BabyBear>(), (1u64 << 31) - 1);
+    }
+}
@@ -0,0 +1,323 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+
+use crate::air::eval;
+use crate::sbox_mode::SboxMode;
+use crate::{Poseidon2Air, Poseidon2Cols};
+
+/// A domain separator that fixes a message's length up front: the capacity lanes are
+/// initialized with the number of field elements in the message (rather than, say, zero or a
+/// running byte count), and the final block is zero-padded -- with the padding itself pinned to
+/// zero by [`Poseidon2SpongeCols::active`], so the padded region can't be repurposed to encode
+/// extra message content. Two messages of different lengths therefore never share an initial
+/// capacity, which rules out trivial length-extension.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConstantLength;
+
+impl ConstantLength {
+    /// The capacity lanes' initial value for a message of `input_len` field elements.
+    pub fn initial_capacity<F: Field, const CAPACITY: usize>(input_len: usize) -> [F; CAPACITY] {
+        let mut capacity = [F::zero(); CAPACITY];
+        capacity[0] = F::from_canonical_usize(input_len);
+        capacity
+    }
+}
+
+/// Columns for one row of a [`Poseidon2SpongeAir`]: a single absorb-then-permute step.
+#[repr(C)]
+pub struct Poseidon2SpongeCols<
+    T,
+    const WIDTH: usize,
+    const RATE: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+    M: SboxMode,
+> {
+    /// The length, in field elements, of the message this row belongs to. Constant across
+    /// every row of the trace under the [`ConstantLength`] domain (see
+    /// [`Poseidon2SpongeAir`] on why a trace holds exactly one message).
+    pub input_len: T,
+    /// 1 if this row absorbs the first block of the message, in which case the capacity lanes
+    /// are freshly initialized rather than carried over from the previous row. Pinned to 1 on
+    /// the first row and 0 on every other row (see [`Poseidon2SpongeAir::eval`]).
+    pub is_first_block: T,
+    /// 1 if this row absorbs the last block of the message, in which case its post-permutation
+    /// rate lanes are the squeezed digest. Pinned to 1 on the last row and 0 on every other row
+    /// (see [`Poseidon2SpongeAir::eval`]).
+    pub is_final_block: T,
+    /// The number of message elements absorbed in every row strictly before this one within the
+    /// same message; zero on a message's first block. Together with `active`, this pins a
+    /// message's final block to exactly `input_len` real elements.
+    pub absorbed_before: T,
+    /// 1 if `block[i]` holds a real message element, 0 if it's padding. Every row but a
+    /// message's final one has `active` all-1 (a full block); the final row's `active` is a
+    /// prefix of 1s followed by a suffix of 0s, so padding only ever trails real elements.
+    pub active: [T; RATE],
+    /// The RATE field elements absorbed into the rate lanes this row, zero-padded past the end
+    /// of the message.
+    pub block: [T; RATE],
+    /// The inner Poseidon2 permutation: `perm.inputs` is the state immediately after this row's
+    /// absorption (and before the permutation is applied).
+    pub perm: Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+}
+
+impl<
+        T: Clone,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    /// The permutation's output state, i.e. the last ending full round's `post` state (after its
+    /// S-boxes and external linear layer).
+    pub(crate) fn output(&self) -> [T; WIDTH] {
+        self.ending_full_rounds[HALF_FULL_ROUNDS - 1].post.clone()
+    }
+}
+
+/// An AIR constraining a Poseidon2 sponge hash: a rate/capacity split over the bare permutation
+/// constrained by [`Poseidon2Air`], with absorb, pad and squeeze linking constraints added on
+/// top so consecutive rows can absorb a single variable-length [`ConstantLength`] message one
+/// block per row.
+///
+/// A trace holds exactly one message: `is_first_block` is pinned to the first row and
+/// `is_final_block` to the last (see [`Self::eval`]), so the squeezed digest exposed through
+/// [`AirBuilderWithPublicValues::public_values`] always binds to that one message's real final
+/// permutation output, never to a vacuously-never-final row. Hashing several messages still
+/// means running several traces of this AIR, one per message, rather than packing them into a
+/// shared trace.
+pub struct Poseidon2SpongeAir<
+    F,
+    const WIDTH: usize,
+    const RATE: usize,
+    const SBOX_DEGREE: usize,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+    M: SboxMode,
+> {
+    permutation: Poseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+}
+
+impl<
+        F,
+        const WIDTH: usize,
+        const RATE: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > Poseidon2SpongeAir<F, WIDTH, RATE, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    pub fn new(
+        permutation: Poseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
+    ) -> Self {
+        assert!(RATE > 0, "the rate must be nonempty");
+        assert!(RATE < WIDTH, "the rate must leave room for a nonempty capacity");
+        Self { permutation }
+    }
+}
+
+impl<
+        F: Field,
+        const WIDTH: usize,
+        const RATE: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > BaseAir<F>
+    for Poseidon2SpongeAir<F, WIDTH, RATE, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    fn width(&self) -> usize {
+        crate::columns::num_cols::<WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>()
+            + 2 * RATE
+            + 4
+    }
+}
+
+impl<
+        AB: AirBuilderWithPublicValues,
+        const WIDTH: usize,
+        const RATE: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        M: SboxMode,
+    > Air<AB>
+    for Poseidon2SpongeAir<AB::F, WIDTH, RATE, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>
+{
+    #[inline]
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let local: &Poseidon2SpongeCols<
+            AB::Var,
+            WIDTH,
+            RATE,
+            SBOX_DEGREE,
+            SBOX_REGISTERS,
+            HALF_FULL_ROUNDS,
+            PARTIAL_ROUNDS,
+            M,
+        > = (*local).borrow();
+        let next: &Poseidon2SpongeCols<
+            AB::Var,
+            WIDTH,
+            RATE,
+            SBOX_DEGREE,
+            SBOX_REGISTERS,
+            HALF_FULL_ROUNDS,
+            PARTIAL_ROUNDS,
+            M,
+        > = (*next).borrow();
+
+        builder.assert_bool(local.is_first_block.clone());
+        builder.assert_bool(local.is_final_block.clone());
+        for i in 0..RATE {
+            builder.assert_bool(local.active[i].clone());
+        }
+
+        // A trace holds exactly one message (see `Poseidon2SpongeAir`'s doc comment): the first
+        // row always starts it and the last row always ends it, so `is_final_block` can't be 0
+        // on every row -- which would otherwise leave the digest-binding constraint below
+        // vacuously true and the public digest unconstrained by the trace.
+        builder.when_first_row().assert_one(local.is_first_block.clone());
+        builder.when_last_row().assert_one(local.is_final_block.clone());
+        builder.when_transition().assert_zero(next.is_first_block.clone());
+        builder.when_transition().assert_zero(local.is_final_block.clone());
+
+        eval(&self.permutation, builder, &local.perm);
+
+        // Only a message's final block may be short: every other block is fully active, and a
+        // padding slot (`active[i] == 0`) is pinned to zero so the prover can't smuggle extra
+        // values past the block boundary while keeping the same declared `input_len`.
+        let is_not_final = AB::Expr::one() - local.is_final_block.clone();
+        for i in 0..RATE {
+            builder.when(is_not_final.clone()).assert_one(local.active[i].clone());
+            builder
+                .when(AB::Expr::one() - local.active[i].clone())
+                .assert_zero(local.block[i].clone());
+        }
+        // `active` is a prefix of 1s followed by a suffix of 0s, so padding only ever trails the
+        // real elements within a block rather than appearing in the middle of one.
+        for i in 0..RATE - 1 {
+            builder
+                .when(AB::Expr::one() - local.active[i].clone())
+                .assert_zero(local.active[i + 1].clone());
+        }
+
+        let active_count: AB::Expr = local.active.iter().cloned().map(Into::into).sum();
+        builder
+            .when(local.is_first_block.clone())
+            .assert_zero(local.absorbed_before.clone());
+        builder.when(local.is_final_block.clone()).assert_eq(
+            local.absorbed_before.clone() + active_count.clone(),
+            local.input_len.clone(),
+        );
+
+        let capacity = WIDTH - RATE;
+
+        // A message's first block absorbs directly into a freshly initialized capacity.
+        for i in 0..RATE {
+            builder
+                .when(local.is_first_block.clone())
+                .assert_eq(local.perm.inputs[i].clone(), local.block[i].clone());
+        }
+        builder.when(local.is_first_block.clone()).assert_eq(
+            local.perm.inputs[RATE].clone(),
+            local.input_len.clone(),
+        );
+        for i in 1..capacity {
+            builder
+                .when(local.is_first_block.clone())
+                .assert_zero(local.perm.inputs[RATE + i].clone());
+        }
+
+        // A continuing block absorbs into the rate left behind by the previous row's
+        // permutation, and carries the previous row's capacity forward unchanged.
+        let local_output = local.perm.output();
+        let continues_local_message = AB::Expr::one() - next.is_first_block.clone();
+        for i in 0..RATE {
+            builder
+                .when_transition()
+                .when(continues_local_message.clone())
+                .assert_eq(
+                    next.perm.inputs[i].clone(),
+                    next.block[i].clone() + local_output[i].clone(),
+                );
+        }
+        for i in 0..capacity {
+            builder
+                .when_transition()
+                .when(continues_local_message.clone())
+                .assert_eq(next.perm.inputs[RATE + i].clone(), local_output[RATE + i].clone());
+        }
+        builder
+            .when_transition()
+            .when(continues_local_message.clone())
+            .assert_eq(
+                next.absorbed_before.clone(),
+                local.absorbed_before.clone() + active_count,
+            );
+        builder
+            .when_transition()
+            .when(continues_local_message)
+            .assert_eq(next.input_len.clone(), local.input_len.clone());
+
+        // The squeezed digest is the rate lanes after the final block's permutation.
+        let public_values = builder.public_values();
+        let digest: Vec<AB::PublicVar> = public_values[..RATE].to_vec();
+        for (output_i, digest_i) in local_output[..RATE].iter().zip(digest) {
+            builder
+                .when(local.is_final_block.clone())
+                .assert_eq(output_i.clone(), digest_i.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::AbstractField;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::sbox_mode::Forward;
+
+    #[test]
+    fn initial_capacity_encodes_the_message_length_in_its_first_lane() {
+        let capacity: [BabyBear; 4] = ConstantLength::initial_capacity(7);
+        assert_eq!(capacity[0], BabyBear::from_canonical_usize(7));
+        assert_eq!(&capacity[1..], &[BabyBear::zero(); 3]);
+    }
+
+    #[test]
+    fn initial_capacity_of_different_lengths_diverge() {
+        let a: [BabyBear; 4] = ConstantLength::initial_capacity(1);
+        let b: [BabyBear; 4] = ConstantLength::initial_capacity(2);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    #[should_panic(expected = "rate must be nonempty")]
+    fn rejects_a_zero_rate() {
+        let permutation: Poseidon2Air<BabyBear, 8, 17, 4, 2, 3, Forward> =
+            Poseidon2Air::new_from_rng(&mut StdRng::seed_from_u64(0));
+        let _: Poseidon2SpongeAir<BabyBear, 8, 0, 17, 4, 2, 3, Forward> =
+            Poseidon2SpongeAir::new(permutation);
+    }
+}
@@ -1,12 +1,13 @@
 use core::borrow::{Borrow, BorrowMut};
 
 use p3_air::{Air, AirBuilder, BaseAir};
-use p3_field::Field;
+use p3_field::{Field, PrimeField64};
 use p3_matrix::Matrix;
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
 
 use crate::air::eval;
+use crate::sbox_mode::SboxMode;
 use crate::{Poseidon2Air, Poseidon2Cols};
 
 /// A "vectorized" version of Poseidon2Cols, for computing multiple Poseidon2 permutations per row.
@@ -19,10 +20,17 @@ pub struct VectorizedPoseidon2Cols<
     const HALF_FULL_ROUNDS: usize,
     const PARTIAL_ROUNDS: usize,
     const VECTOR_LEN: usize,
+    M: SboxMode,
 > {
-    pub(crate) cols:
-        [Poseidon2Cols<T, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS>;
-            VECTOR_LEN],
+    pub(crate) cols: [Poseidon2Cols<
+        T,
+        WIDTH,
+        SBOX_DEGREE,
+        SBOX_REGISTERS,
+        HALF_FULL_ROUNDS,
+        PARTIAL_ROUNDS,
+        M,
+    >; VECTOR_LEN],
 }
 
 impl<
@@ -33,6 +41,7 @@ impl<
         const HALF_FULL_ROUNDS: usize,
         const PARTIAL_ROUNDS: usize,
         const VECTOR_LEN: usize,
+        M: SboxMode,
     >
     Borrow<
         VectorizedPoseidon2Cols<
@@ -43,6 +52,7 @@ impl<
             HALF_FULL_ROUNDS,
             PARTIAL_ROUNDS,
             VECTOR_LEN,
+            M,
         >,
     > for [T]
 {
@@ -56,6 +66,7 @@ impl<
         HALF_FULL_ROUNDS,
         PARTIAL_ROUNDS,
         VECTOR_LEN,
+        M,
     > {
         // debug_assert_eq!(self.len(), NUM_COLS);
         let (prefix, shorts, suffix) = unsafe {
@@ -67,6 +78,7 @@ impl<
                 HALF_FULL_ROUNDS,
                 PARTIAL_ROUNDS,
                 VECTOR_LEN,
+                M,
             >>()
         };
         debug_assert!(prefix.is_empty(), "Alignment should match");
@@ -84,6 +96,7 @@ impl<
         const HALF_FULL_ROUNDS: usize,
         const PARTIAL_ROUNDS: usize,
         const VECTOR_LEN: usize,
+        M: SboxMode,
     >
     BorrowMut<
         VectorizedPoseidon2Cols<
@@ -94,6 +107,7 @@ impl<
             HALF_FULL_ROUNDS,
             PARTIAL_ROUNDS,
             VECTOR_LEN,
+            M,
         >,
     > for [T]
 {
@@ -107,6 +121,7 @@ impl<
         HALF_FULL_ROUNDS,
         PARTIAL_ROUNDS,
         VECTOR_LEN,
+        M,
     > {
         // debug_assert_eq!(self.len(), NUM_COLS);
         let (prefix, shorts, suffix) = unsafe {
@@ -118,6 +133,7 @@ impl<
                 HALF_FULL_ROUNDS,
                 PARTIAL_ROUNDS,
                 VECTOR_LEN,
+                M,
             >>()
         };
         debug_assert!(prefix.is_empty(), "Alignment should match");
@@ -136,8 +152,9 @@ pub struct VectorizedPoseidon2Air<
     const HALF_FULL_ROUNDS: usize,
     const PARTIAL_ROUNDS: usize,
     const VECTOR_LEN: usize,
+    M: SboxMode,
 > {
-    air: Poseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS>,
+    air: Poseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M>,
 }
 
 impl<
@@ -148,6 +165,7 @@ impl<
         const HALF_FULL_ROUNDS: usize,
         const PARTIAL_ROUNDS: usize,
         const VECTOR_LEN: usize,
+        M: SboxMode,
     >
     VectorizedPoseidon2Air<
         F,
@@ -157,6 +175,7 @@ impl<
         HALF_FULL_ROUNDS,
         PARTIAL_ROUNDS,
         VECTOR_LEN,
+        M,
     >
 {
     pub fn new_from_rng<R: Rng>(rng: &mut R) -> Self
@@ -169,6 +188,67 @@ impl<
     }
 }
 
+impl<
+        F: PrimeField64,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        const VECTOR_LEN: usize,
+        M: SboxMode,
+    >
+    VectorizedPoseidon2Air<
+        F,
+        WIDTH,
+        SBOX_DEGREE,
+        SBOX_REGISTERS,
+        HALF_FULL_ROUNDS,
+        PARTIAL_ROUNDS,
+        VECTOR_LEN,
+        M,
+    >
+{
+    /// Builds every lane's [`Poseidon2Air`] from the same domain-separated `seed`, so a prover
+    /// and a verifier that agree on `seed` always agree on the round constants.
+    pub fn new_from_seed(seed: &[u8]) -> Self {
+        Self {
+            air: Poseidon2Air::new_from_seed(seed),
+        }
+    }
+}
+
+impl<
+        F: Field,
+        const WIDTH: usize,
+        const SBOX_DEGREE: usize,
+        const SBOX_REGISTERS: usize,
+        const HALF_FULL_ROUNDS: usize,
+        const PARTIAL_ROUNDS: usize,
+        const VECTOR_LEN: usize,
+        M: SboxMode,
+    >
+    VectorizedPoseidon2Air<
+        F,
+        WIDTH,
+        SBOX_DEGREE,
+        SBOX_REGISTERS,
+        HALF_FULL_ROUNDS,
+        PARTIAL_ROUNDS,
+        VECTOR_LEN,
+        M,
+    >
+{
+    /// The underlying per-lane [`Poseidon2Air`], for callers (such as
+    /// [`crate::merkle::MerklePathAir`]) that need to constrain a single permutation directly
+    /// rather than through [`VectorizedPoseidon2Cols`]'s fixed-size lane array.
+    pub(crate) fn permutation(
+        &self,
+    ) -> &Poseidon2Air<F, WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS, M> {
+        &self.air
+    }
+}
+
 impl<
         F: Field,
         const WIDTH: usize,
@@ -177,6 +257,7 @@ impl<
         const HALF_FULL_ROUNDS: usize,
         const PARTIAL_ROUNDS: usize,
         const VECTOR_LEN: usize,
+        M: SboxMode,
     > BaseAir<F>
     for VectorizedPoseidon2Air<
         F,
@@ -186,6 +267,7 @@ impl<
         HALF_FULL_ROUNDS,
         PARTIAL_ROUNDS,
         VECTOR_LEN,
+        M,
     >
 {
     fn width(&self) -> usize {
@@ -201,6 +283,7 @@ impl<
         const HALF_FULL_ROUNDS: usize,
         const PARTIAL_ROUNDS: usize,
         const VECTOR_LEN: usize,
+        M: SboxMode,
     > Air<AB>
     for VectorizedPoseidon2Air<
         AB::F,
@@ -210,6 +293,7 @@ impl<
         HALF_FULL_ROUNDS,
         PARTIAL_ROUNDS,
         VECTOR_LEN,
+        M,
     >
 {
     #[inline]
@@ -224,9 +308,10 @@ impl<
             HALF_FULL_ROUNDS,
             PARTIAL_ROUNDS,
             VECTOR_LEN,
+            M,
         > = (*local).borrow();
         for perm in &local.cols {
             eval(&self.air, builder, perm);
         }
     }
-}
\ No newline at end of file
+}